@@ -0,0 +1,64 @@
+//! An orbiting camera: yaw/pitch around a fixed target, with a toggle between orthographic and
+//! perspective projection.
+
+use glam::{EulerRot, Mat3, Quat, Vec2, Vec3};
+
+/// How far the pitch is kept away from straight up/down, to avoid the trackball flipping over.
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    Orthographic,
+    Perspective,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Distance used as the perspective focal length. Has no effect in orthographic projection.
+    pub distance: f32,
+    pub projection: Projection,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 10.0,
+            projection: Projection::Orthographic,
+        }
+    }
+}
+
+impl Camera {
+    /// Rotates the camera by `dyaw`/`dpitch` radians, clamping pitch so the view cannot flip
+    /// over.
+    pub fn rotate(&mut self, dyaw: f32, dpitch: f32) {
+        self.yaw += dyaw;
+        self.pitch = (self.pitch + dpitch).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+
+    /// The rotation matrix that carries a world-space position into camera space.
+    pub fn view_rotation(&self) -> Mat3 {
+        Mat3::from_quat(Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0))
+    }
+
+    /// Rotates `position` from world space into camera space.
+    pub fn to_camera_space(&self, position: Vec3) -> Vec3 {
+        self.view_rotation() * position
+    }
+
+    /// Projects a camera-space position down to 2D screen-space coordinates (before zoom and
+    /// screen-center offset).
+    pub fn project(&self, camera_space: Vec3) -> Vec2 {
+        match self.projection {
+            Projection::Orthographic => Vec2::new(camera_space.x, camera_space.y),
+            Projection::Perspective => {
+                let depth = (self.distance - camera_space.z).max(0.01);
+                Vec2::new(camera_space.x, camera_space.y) * (self.distance / depth)
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! A text console for reading and setting [`CVar`](crate::cvar::CVar)s at runtime.
+//!
+//! Toggled with the backtick key. While open, typed characters are appended to an input line;
+//! Enter parses and runs it as `name value` (set), `name` (get), `list`, or `help [name]`, and
+//! the result is kept around as the last console output line. The input line and output are
+//! echoed to stderr; nothing is drawn in the window.
+
+use crate::cvar::CVarRegistry;
+
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    buffer: String,
+    /// The result of the last command that was run, echoed after the input line.
+    last_output: String,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.buffer.clear();
+    }
+
+    pub fn input_line(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn last_output(&self) -> &str {
+        &self.last_output
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.buffer.push(c);
+    }
+
+    pub fn backspace(&mut self) {
+        self.buffer.pop();
+    }
+
+    /// Runs the current input line as a command against `registry` and clears it.
+    pub fn submit(&mut self, registry: &CVarRegistry) {
+        let line = std::mem::take(&mut self.buffer);
+        self.last_output = run_command(&line, registry);
+    }
+}
+
+fn run_command(line: &str, registry: &CVarRegistry) -> String {
+    let line = line.trim();
+    if line.is_empty() {
+        return String::new();
+    }
+    // Split off only the command/name, so a value containing spaces (e.g. a `selection`
+    // expression like `resname POPC`) is passed through untouched rather than re-tokenized.
+    let (command, rest) = line
+        .split_once(char::is_whitespace)
+        .unwrap_or((line, ""));
+    let rest = rest.trim_start();
+
+    match command {
+        "list" => registry
+            .iter()
+            .map(|var| var.name().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        "help" => match rest.split_whitespace().next() {
+            Some(name) => match registry.get(name) {
+                Some(var) => format!("{}: {}", var.name(), var.description()),
+                None => format!("unknown cvar '{name}'"),
+            },
+            None => "usage: list | help <name> | <name> | <name> <value>".to_string(),
+        },
+        name => match registry.get(name) {
+            None => format!("unknown cvar '{name}'"),
+            Some(var) => {
+                if rest.is_empty() {
+                    format!("{name} = {}", var.serialize())
+                } else {
+                    match var.deserialize(rest) {
+                        Ok(()) => format!("{name} = {rest}"),
+                        Err(err) => err,
+                    }
+                }
+            }
+        },
+    }
+}
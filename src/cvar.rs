@@ -0,0 +1,163 @@
+//! A CVar (console variable) registry for runtime-tunable render settings.
+//!
+//! Each [`CVar<T>`] wraps a shared, interior-mutable value plus a name and description, so the
+//! same handle held by `main` for rendering can also be registered as a [`Var`] trait object and
+//! driven from the [console](crate::console).
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// An RGB color, serialized as `"r,g,b"` for CVar get/set and the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub [u8; 3]);
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.0[0], self.0[1], self.0[2])
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut channels = s.split(',').map(|c| c.trim().parse::<u8>());
+        let (Some(Ok(r)), Some(Ok(g)), Some(Ok(b)), None) = (
+            channels.next(),
+            channels.next(),
+            channels.next(),
+            channels.next(),
+        ) else {
+            return Err("expected three comma-separated values 0-255, e.g. '20,20,30'");
+        };
+        Ok(Rgb([r, g, b]))
+    }
+}
+
+/// A named, described, runtime-settable value, type-erased so heterogeneous [`CVar<T>`]s can
+/// share one registry.
+pub trait Var {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    fn serialize(&self) -> String;
+    fn deserialize(&self, value: &str) -> Result<(), String>;
+}
+
+/// A single typed console variable.
+///
+/// Cloning a `CVar` is cheap and shares the same underlying value, so `main` can keep a typed
+/// handle for rendering while an equally-cheap clone is registered in the [`CVarRegistry`].
+#[derive(Clone)]
+pub struct CVar<T> {
+    name: &'static str,
+    description: &'static str,
+    value: Rc<RefCell<T>>,
+}
+
+impl<T: Clone> CVar<T> {
+    pub fn new(name: &'static str, description: &'static str, default: T) -> Self {
+        Self {
+            name,
+            description,
+            value: Rc::new(RefCell::new(default)),
+        }
+    }
+
+    pub fn get(&self) -> T {
+        self.value.borrow().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.value.borrow_mut() = value;
+    }
+}
+
+impl<T> Var for CVar<T>
+where
+    T: Clone + fmt::Display + FromStr,
+{
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn serialize(&self) -> String {
+        self.get().to_string()
+    }
+
+    fn deserialize(&self, value: &str) -> Result<(), String> {
+        let parsed = value
+            .parse()
+            .map_err(|_| format!("'{value}' is not a valid value for '{}'", self.name))?;
+        self.set(parsed);
+        Ok(())
+    }
+}
+
+/// A registry of [`Var`]s, looked up by name for the console and for config file save/load.
+#[derive(Default)]
+pub struct CVarRegistry {
+    vars: Vec<Box<dyn Var>>,
+}
+
+impl CVarRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, var: impl Var + 'static) {
+        self.vars.push(Box::new(var));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Var> {
+        self.vars
+            .iter()
+            .find(|v| v.name() == name)
+            .map(|v| v.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Var> {
+        self.vars.iter().map(|v| v.as_ref())
+    }
+
+    /// Writes `name value` pairs, one per line, to `path`.
+    pub fn save_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let contents: String = self
+            .vars
+            .iter()
+            .map(|v| format!("{} {}\n", v.name(), v.serialize()))
+            .collect();
+        std::fs::write(path, contents)
+    }
+
+    /// Reads `name value` pairs from `path` and applies each to the matching registered var.
+    /// Unknown names and malformed lines are skipped with a warning rather than failing the
+    /// whole load, since a stale config file shouldn't prevent startup.
+    pub fn load_from_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let contents = std::fs::read_to_string(path)?;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((name, value)) = line.split_once(' ') else {
+                eprintln!("WARN:  Ignoring malformed config line: '{line}'");
+                continue;
+            };
+            match self.get(name) {
+                Some(var) => {
+                    if let Err(err) = var.deserialize(value) {
+                        eprintln!("WARN:  Ignoring config line '{line}': {err}");
+                    }
+                }
+                None => eprintln!("WARN:  Ignoring config line for unknown cvar '{name}'"),
+            }
+        }
+        Ok(())
+    }
+}
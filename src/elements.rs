@@ -0,0 +1,76 @@
+//! Per-element color and van der Waals radius lookups, keyed by the element guessed from an
+//! atom's `.gro`/`.trr` atom name.
+
+/// An RGBA color in `[0, 1]`.
+pub type Color = [f32; 4];
+
+/// Color and van der Waals radius (nm) for one element, in the conventional CPK scheme.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementProperties {
+    pub color: Color,
+    pub vdw_radius: f32,
+}
+
+const UNKNOWN: ElementProperties = ElementProperties {
+    color: [1.0, 0.0, 1.0, 1.0],
+    vdw_radius: 0.15,
+};
+
+const TABLE: &[(&str, ElementProperties)] = &[
+    (
+        "H",
+        ElementProperties {
+            color: [1.0, 1.0, 1.0, 1.0],
+            vdw_radius: 0.110,
+        },
+    ),
+    (
+        "C",
+        ElementProperties {
+            color: [0.3, 0.3, 0.3, 1.0],
+            vdw_radius: 0.170,
+        },
+    ),
+    (
+        "N",
+        ElementProperties {
+            color: [0.2, 0.2, 1.0, 1.0],
+            vdw_radius: 0.155,
+        },
+    ),
+    (
+        "O",
+        ElementProperties {
+            color: [1.0, 0.15, 0.15, 1.0],
+            vdw_radius: 0.152,
+        },
+    ),
+    (
+        "P",
+        ElementProperties {
+            color: [1.0, 0.6, 0.0, 1.0],
+            vdw_radius: 0.180,
+        },
+    ),
+    (
+        "S",
+        ElementProperties {
+            color: [1.0, 0.9, 0.2, 1.0],
+            vdw_radius: 0.180,
+        },
+    ),
+];
+
+/// Guesses the element from a `.gro`/`.trr` atom name (e.g. `CA`, `HB1`, `OW`) and returns its
+/// conventional color and van der Waals radius.
+///
+/// `.gro` atom names are not a reliable element identifier (`CA` is an alpha-carbon, not
+/// calcium), so this takes the simple approach of matching the leading letter, which is right
+/// for the common biomolecular elements (C, N, O, H, P, S).
+pub fn lookup(atomname: &str) -> ElementProperties {
+    let first = atomname.chars().next().unwrap_or(' ').to_ascii_uppercase();
+    TABLE
+        .iter()
+        .find(|(symbol, _)| symbol.chars().next() == Some(first))
+        .map_or(UNKNOWN, |(_, props)| *props)
+}
@@ -0,0 +1,483 @@
+//! GPU-instanced sphere-impostor renderer.
+//!
+//! Uploads atom positions as an instance buffer and draws screen-facing quads whose fragment
+//! shader computes a sphere normal and discards fragments outside the disc, writing true depth
+//! so overlapping atoms occlude correctly. This is the standard billboard-impostor technique for
+//! drawing large systems at interactive rates, and replaces the CPU framebuffer fill for
+//! adapters that support it; [`GpuRenderer::new`] returns `None` so callers can fall back to the
+//! CPU path otherwise.
+
+use pixels::{wgpu, Pixels, PixelsContext};
+
+use crate::camera::{Camera, Projection};
+use crate::elements;
+use crate::structure::Atom;
+
+const SHADER_SOURCE: &str = include_str!("shaders/sphere_impostor.wgsl");
+
+/// The `atom_size` CVar's default; `atom_scale` is `atom_size / DEFAULT_ATOM_SIZE`, so the
+/// default atom size renders at the same radius the element table already specifies.
+const DEFAULT_ATOM_SIZE: f32 = 2.0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Instance {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 4],
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Float32x4];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    fn as_bytes(instances: &[Instance]) -> &[u8] {
+        // SAFETY: `Instance` is `#[repr(C)]` and made up entirely of `f32`s, so it has no
+        // padding and no invalid bit patterns.
+        unsafe {
+            std::slice::from_raw_parts(
+                instances.as_ptr() as *const u8,
+                std::mem::size_of_val(instances),
+            )
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Uniforms {
+    // `mat3x3<f32>` in WGSL is laid out as three padded `vec4`s.
+    view_rotation: [[f32; 4]; 3],
+    screen_size: [f32; 2],
+    zoom: f32,
+    distance: f32,
+    perspective: u32,
+    atom_scale: f32,
+    depth_shading: u32,
+    _padding: f32,
+}
+
+impl Uniforms {
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: `Uniforms` is `#[repr(C)]` and made up entirely of `f32`/`u32`, so it has no
+        // invalid bit patterns.
+        unsafe {
+            std::slice::from_raw_parts(
+                (self as *const Self) as *const u8,
+                std::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
+
+/// Renders atoms as GPU-instanced sphere impostors instead of filling the CPU framebuffer
+/// atom-by-atom.
+pub struct GpuRenderer {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+    depth_texture: wgpu::TextureView,
+    depth_size: (u32, u32),
+    render_format: wgpu::TextureFormat,
+    // Render target we own (unlike `render_target`, which is pixels's surface view and can't be
+    // read back from), so `take_screenshot` has something to copy out of.
+    capture_texture: wgpu::Texture,
+    capture_view: wgpu::TextureView,
+    capture_buffer: wgpu::Buffer,
+    capture_padded_bytes_per_row: u32,
+    capture_size: (u32, u32),
+}
+
+impl GpuRenderer {
+    /// Builds the pipeline and buffers needed to render with `pixels`'s wgpu device, or returns
+    /// `None` if the device can't satisfy this pipeline (e.g. no `Depth32Float` render-attachment
+    /// support).
+    ///
+    /// Pipeline creation is wrapped in a validation error scope rather than let straight through,
+    /// since an unsupported device fails `create_render_pipeline` via a panicking validation
+    /// error, not a `Result`.
+    pub fn new(pixels: &Pixels) -> Option<Self> {
+        let device = pixels.device();
+        let render_format = pixels.render_texture_format();
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("sphere impostor shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER_SOURCE.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sphere impostor bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sphere impostor uniforms"),
+            size: std::mem::size_of::<Uniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sphere impostor bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sphere impostor pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sphere impostor pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Instance::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: render_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let instance_capacity = 1024;
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sphere impostor instances"),
+            size: (instance_capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let depth_texture = Self::make_depth_texture(device, 1, 1);
+        let (capture_texture, capture_view) = Self::make_capture_texture(device, 1, 1, render_format);
+        let (capture_buffer, capture_padded_bytes_per_row) =
+            Self::make_capture_buffer(device, 1, 1);
+
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            eprintln!("WARN:  GPU renderer pipeline failed validation: {error}");
+            return None;
+        }
+
+        Some(Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            instance_buffer,
+            instance_capacity,
+            depth_texture,
+            depth_size: (1, 1),
+            render_format,
+            capture_texture,
+            capture_view,
+            capture_buffer,
+            capture_padded_bytes_per_row,
+            capture_size: (1, 1),
+        })
+    }
+
+    fn make_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("sphere impostor depth buffer"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// A second color target we own (and can therefore read back from), rendered into alongside
+    /// `render_target` so `take_screenshot` reflects what's actually on screen. `render_target`
+    /// itself is pixels's swapchain surface view, which isn't copyable.
+    fn make_capture_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("sphere impostor screenshot capture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    /// A readback buffer sized for `width`x`height`, with each row padded up to
+    /// `COPY_BYTES_PER_ROW_ALIGNMENT` as `copy_texture_to_buffer` requires.
+    fn make_capture_buffer(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Buffer, u32) {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let bytes_per_row = width.max(1) * 4;
+        let padded_bytes_per_row = (bytes_per_row + align - 1) / align * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sphere impostor screenshot readback"),
+            size: (padded_bytes_per_row * height.max(1)) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        (buffer, padded_bytes_per_row)
+    }
+
+    /// Renders `atoms` as sphere impostors into `render_target`, recording commands into
+    /// `encoder`. Resizes internal buffers as needed for `width`/`height` and the atom count.
+    pub fn render(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        render_target: &wgpu::TextureView,
+        context: &PixelsContext,
+        atoms: &[&Atom],
+        camera: &Camera,
+        zoom: f32,
+        atom_size: u32,
+        depth_shading: bool,
+        background: [u8; 3],
+        width: u32,
+        height: u32,
+    ) {
+        let clear_color = wgpu::Color {
+            r: background[0] as f64 / 255.0,
+            g: background[1] as f64 / 255.0,
+            b: background[2] as f64 / 255.0,
+            a: 1.0,
+        };
+        if self.depth_size != (width, height) {
+            self.depth_texture = Self::make_depth_texture(&context.device, width, height);
+            self.depth_size = (width, height);
+        }
+
+        if self.capture_size != (width, height) {
+            let (capture_texture, capture_view) =
+                Self::make_capture_texture(&context.device, width, height, self.render_format);
+            let (capture_buffer, capture_padded_bytes_per_row) =
+                Self::make_capture_buffer(&context.device, width, height);
+            self.capture_texture = capture_texture;
+            self.capture_view = capture_view;
+            self.capture_buffer = capture_buffer;
+            self.capture_padded_bytes_per_row = capture_padded_bytes_per_row;
+            self.capture_size = (width, height);
+        }
+
+        let instances: Vec<Instance> = atoms
+            .iter()
+            .map(|atom| {
+                let element = elements::lookup(&atom.atomname);
+                Instance {
+                    position: atom.position.to_array(),
+                    radius: element.vdw_radius,
+                    color: element.color,
+                }
+            })
+            .collect();
+
+        if instances.len() > self.instance_capacity {
+            self.instance_capacity = instances.len().next_power_of_two();
+            self.instance_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("sphere impostor instances"),
+                size: (self.instance_capacity * std::mem::size_of::<Instance>())
+                    as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        context
+            .queue
+            .write_buffer(&self.instance_buffer, 0, Instance::as_bytes(&instances));
+
+        let view_rotation = camera.view_rotation();
+        let uniforms = Uniforms {
+            // Pad each column to a `vec4` to match WGSL's `mat3x3` layout.
+            view_rotation: view_rotation
+                .to_cols_array_2d()
+                .map(|[x, y, z]| [x, y, z, 0.0]),
+            screen_size: [width as f32, height as f32],
+            zoom,
+            distance: camera.distance,
+            perspective: matches!(camera.projection, Projection::Perspective) as u32,
+            atom_scale: atom_size as f32 / DEFAULT_ATOM_SIZE,
+            depth_shading: depth_shading as u32,
+            _padding: 0.0,
+        };
+        context
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, uniforms.as_bytes());
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("sphere impostor pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..4, 0..instances.len() as u32);
+        drop(pass);
+
+        // Render the same frame again into our own readable texture, purely so a screenshot can
+        // later copy out of it; `render_target` is pixels's swapchain view and can't be copied
+        // from directly.
+        let mut capture_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("sphere impostor capture pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.capture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        capture_pass.set_pipeline(&self.pipeline);
+        capture_pass.set_bind_group(0, &self.bind_group, &[]);
+        capture_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        capture_pass.draw(0..4, 0..instances.len() as u32);
+        drop(capture_pass);
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.capture_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.capture_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.capture_padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// The pixel dimensions of the texture `take_screenshot` will read back.
+    pub fn capture_size(&self) -> (u32, u32) {
+        self.capture_size
+    }
+
+    /// Reads back the last rendered frame as tightly-packed RGBA8 rows, blocking until the copy
+    /// recorded in the previous [`GpuRenderer::render`] call has completed on the GPU.
+    pub fn take_screenshot(&self, device: &wgpu::Device) -> Vec<u8> {
+        let (width, height) = self.capture_size;
+        let bgra = matches!(
+            self.render_format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        let slice = self.capture_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .expect("map_async callback should have run after polling the device")
+            .expect("mapping the screenshot readback buffer failed");
+
+        let padded = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in padded.chunks(self.capture_padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..width as usize * 4]);
+        }
+        drop(padded);
+        self.capture_buffer.unmap();
+
+        if bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        pixels
+    }
+}
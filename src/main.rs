@@ -1,39 +1,271 @@
 #![feature(iter_next_chunk)]
 
+mod camera;
+mod console;
+mod cvar;
+mod elements;
+mod gpu_renderer;
+mod screenshot;
+mod selection;
 mod structure;
+mod xdr;
 
 use std::io::Read;
+use std::time::{Duration, Instant};
 
-use glam::Vec2;
+use camera::{Camera, Projection};
+use console::Console;
+use cvar::{CVar, CVarRegistry, Rgb};
+use glam::{Vec2, Vec3};
+use gpu_renderer::GpuRenderer;
 use pixels::{wgpu::Extent3d, Pixels, SurfaceTexture};
-use structure::Structure;
-use winit::event::{Event, VirtualKeyCode};
+use selection::Selection;
+use structure::{Structure, Trajectory};
+use winit::event::{Event, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::{dpi::PhysicalSize, window::WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
+/// How long a played-back frame stays on screen before advancing to the next one.
+const FRAME_DURATION: Duration = Duration::from_millis(33);
+
+/// Radians the camera rotates per frame while an arrow key is held.
+const KEY_ROTATE_SPEED: f32 = 0.03;
+
+/// Radians the camera rotates per pixel of trackball mouse drag.
+const MOUSE_ROTATE_SPEED: f32 = 0.005;
+
+/// Where CVars are saved to and loaded from on startup.
+const CONFIG_PATH: &str = "laurel.cfg";
+
+/// Either a single static structure, or a loaded trajectory being scrubbed through frame by
+/// frame.
+enum Playback {
+    Single(Structure),
+    Trajectory {
+        trajectory: Trajectory,
+        frame: usize,
+        playing: bool,
+        last_advance: Instant,
+    },
+}
+
+impl Playback {
+    fn current(&self) -> &Structure {
+        match self {
+            Playback::Single(structure) => structure,
+            Playback::Trajectory {
+                trajectory, frame, ..
+            } => trajectory
+                .frame(*frame)
+                .expect("playback frame index is always in bounds"),
+        }
+    }
+
+    fn center_structure(&mut self) {
+        match self {
+            Playback::Single(structure) => structure.center_structure(),
+            Playback::Trajectory { trajectory, .. } => {
+                for frame in &mut trajectory.frames {
+                    frame.center_structure();
+                }
+            }
+        }
+    }
+
+    /// Like [`Playback::center_structure`], but centers each frame on the centroid of the atoms
+    /// matching `selection` in that frame, rather than on every atom.
+    fn center_on_selection(&mut self, selection: &Selection) {
+        match self {
+            Playback::Single(structure) => {
+                let indices = structure.select(selection);
+                structure.center_structure_on(&indices);
+            }
+            Playback::Trajectory { trajectory, .. } => {
+                for frame in &mut trajectory.frames {
+                    let indices = frame.select(selection);
+                    frame.center_structure_on(&indices);
+                }
+            }
+        }
+    }
+
+    /// Toggles play/pause. No-op for a single static structure.
+    fn toggle_playing(&mut self) {
+        if let Playback::Trajectory {
+            playing,
+            last_advance,
+            ..
+        } = self
+        {
+            *playing = !*playing;
+            *last_advance = Instant::now();
+        }
+    }
+
+    /// Steps to the next (`forward = true`) or previous frame, clamped to the trajectory's
+    /// bounds. No-op for a single static structure.
+    fn step(&mut self, forward: bool) {
+        if let Playback::Trajectory {
+            trajectory, frame, ..
+        } = self
+        {
+            let last = trajectory.n_frames().saturating_sub(1);
+            *frame = if forward {
+                (*frame + 1).min(last)
+            } else {
+                frame.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Whether playback is currently running. Always `false` for a single static structure.
+    fn is_playing(&self) -> bool {
+        matches!(self, Playback::Trajectory { playing: true, .. })
+    }
+
+    /// Advances playback by one frame if enough time has passed since the last advance. Returns
+    /// whether a frame change occurred.
+    fn tick(&mut self) -> bool {
+        if let Playback::Trajectory {
+            trajectory,
+            frame,
+            playing,
+            last_advance,
+        } = self
+        {
+            if *playing && last_advance.elapsed() >= FRAME_DURATION {
+                *frame = (*frame + 1) % trajectory.n_frames().max(1);
+                *last_advance = Instant::now();
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Reads and parses the `.gro` file at `path`, printing a line-numbered parse error and exiting
+/// cleanly (rather than panicking) if the file can't be read or is malformed.
+fn load_gro(path: &str) -> Structure {
+    let mut gro = String::new();
+    match std::fs::File::open(path) {
+        Ok(mut file) => {
+            if let Err(err) = file.read_to_string(&mut gro) {
+                eprintln!("ERROR: Failed to read '{path}': {err}");
+                std::process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("ERROR: Structure file not found: '{path}': {err}");
+            std::process::exit(1);
+        }
+    }
+    Structure::from_gro(gro).unwrap_or_else(|err| {
+        eprintln!("ERROR: '{path}' is not a valid .gro file: {err}");
+        std::process::exit(1);
+    })
+}
+
+/// Reads the raw bytes of the `.trr` file at `path`, exiting cleanly (rather than panicking) if
+/// it can't be read.
+fn load_trr_bytes(path: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match std::fs::File::open(path) {
+        Ok(mut file) => {
+            if let Err(err) = file.read_to_end(&mut bytes) {
+                eprintln!("ERROR: Failed to read '{path}': {err}");
+                std::process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("ERROR: Trajectory file not found: '{path}': {err}");
+            std::process::exit(1);
+        }
+    }
+    bytes
+}
+
 fn main() -> Result<(), pixels::Error> {
-    let mut structure = {
+    let mut playback = {
         let mut args = std::env::args().skip(1);
         let path = args.next().expect("no path to structure file specified");
-        let mut structure_file =
-            std::fs::File::open(path).expect("structure file not found: '{path}'");
-        let mut gro = String::new();
-        structure_file.read_to_string(&mut gro).unwrap();
 
-        Structure::from_gro(gro).expect("gro file is invalid")
+        if path.ends_with(".trr") {
+            let bytes = load_trr_bytes(&path);
+
+            let topology = args.next().map(|gro_path| load_gro(&gro_path));
+
+            let trajectory = Structure::from_trr_with_topology(&bytes, topology.as_ref())
+                .expect("trr file is invalid");
+
+            if trajectory.n_frames() == 0 {
+                eprintln!("ERROR: '{path}' contains no frames");
+                std::process::exit(1);
+            }
+
+            Playback::Trajectory {
+                trajectory,
+                frame: 0,
+                playing: false,
+                last_advance: Instant::now(),
+            }
+        } else {
+            Playback::Single(load_gro(&path))
+        }
     };
 
-    eprintln!("Structure loaded: '{}'", structure.title);
-    eprintln!("         n_atoms: {}", structure.n_atoms());
-    eprintln!("          center: {}", structure.center());
-    eprintln!("             box: {:?}", structure.box_vecs);
+    {
+        let structure = playback.current();
+        eprintln!("Structure loaded: '{}'", structure.title);
+        eprintln!("         n_atoms: {}", structure.n_atoms());
+        eprintln!("          center: {}", structure.center());
+        eprintln!("             box: {:?}", structure.box_vecs);
+    }
 
     eprintln!("Centering the structure...");
-    structure.center_structure();
-    eprintln!("        centered: {}", structure.center());
+    playback.center_structure();
+    eprintln!("        centered: {}", playback.current().center());
+
+    let zoom = CVar::new("zoom", "camera zoom factor, in pixels per nm", 100.0f32);
+    let perspective = CVar::new(
+        "perspective",
+        "use perspective (true) or orthographic (false) projection",
+        false,
+    );
+    let background = CVar::new("background", "background color, as 'r,g,b'", Rgb([0, 0, 0]));
+    let atom_size = CVar::new(
+        "atom_size",
+        "atom marker size, in pixels (CPU renderer); radius scale relative to 2 (GPU renderer)",
+        2u32,
+    );
+    let depth_shading = CVar::new(
+        "depth_shading",
+        "shade atoms by camera-space depth (CPU renderer) or sphere surface normal (GPU renderer) instead of drawing them at full brightness",
+        true,
+    );
+    let selection = CVar::new(
+        "selection",
+        "selection expression for atoms to show, or empty to show all atoms",
+        String::new(),
+    );
 
-    let mut zoom = 100.0;
+    let mut cvars = CVarRegistry::new();
+    cvars.register(zoom.clone());
+    cvars.register(perspective.clone());
+    cvars.register(background.clone());
+    cvars.register(atom_size.clone());
+    cvars.register(depth_shading.clone());
+    cvars.register(selection.clone());
+
+    let config_path = std::path::Path::new(CONFIG_PATH);
+    if config_path.exists() {
+        if let Err(err) = cvars.load_from_file(config_path) {
+            eprintln!("WARN:  Failed to load '{CONFIG_PATH}': {err}");
+        }
+    }
+
+    let mut console = Console::new();
+    let mut camera = Camera::default();
 
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
@@ -48,74 +280,164 @@ fn main() -> Result<(), pixels::Error> {
         Pixels::new(width, height, surface_texture)?
     };
 
+    let mut gpu_renderer = GpuRenderer::new(&pixels);
+    if gpu_renderer.is_none() {
+        eprintln!("WARN:  GPU renderer could not be set up on this device, falling back to the CPU renderer");
+    }
+
     event_loop.run(move |event, _, control_flow| {
         dbg!(&event);
-        *control_flow = ControlFlow::Wait;
+        *control_flow = if playback.is_playing() {
+            ControlFlow::Poll
+        } else {
+            ControlFlow::Wait
+        };
 
         if let Event::RedrawRequested(_) = event {
             eprintln!("INFO:  Redrawing");
             let Extent3d { width, height, .. } = pixels.texture().size();
 
-            // Render the pixels.
-            let frame = pixels.frame_mut();
-            frame.fill(0x00); // Clear the screen.
-            const PIXEL_SIZE: usize = 4;
-            let screen_center = Vec2::new(width as f32 / 2.0, height as f32 / 2.0);
-            let x_range = 0..width;
-            let y_range = 0..height;
-            for atom in &structure.atoms {
-                // Find the render position.
-                //
-                // Orthographic projection.
-                // |bx| = |sx  0  0||ax| + |cx|
-                // |by| = | 0  0 sy||ay| + |cz|
-                //                  |az|
-                //
-                // From this, we can derive:
-                // bx = sx * ax + cx
-                // by = sz * az + cz
-                let a = atom.position;
-                let cx = 1.0;
-                let cz = 1.0;
-                let sx = 1.0;
-                let sz = 1.0;
-                let bx = sx * a.x + cx;
-                let by = sz * a.z + cz;
-
-                let pos: Vec2 = Vec2::new(bx, by); // TODO
-
-                // Render that onto the screen.
-                let screen_pos = pos * zoom + screen_center;
-                let (x, y) = (screen_pos.x as u32, screen_pos.y as u32);
-                if x_range.contains(&x) && y_range.contains(&y) {
-                    let depth = 1.0;
-                    // let depth = (atom.position.z - structure.min_z()) / (structure.min_z().abs() + structure.max_z());
-                    let px = {
-                        let v = (depth * u8::MAX as f32 + 10.0) as u8;
-                        [v, v, v, 0xff]
-                    };
-                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
-                        let (x, y) = (x + dx, y + dy);
-                        let idx = (y * width + x) as usize * PIXEL_SIZE;
-                        if idx + PIXEL_SIZE >= frame.len() {
-                            continue;
+            camera.projection = if perspective.get() {
+                Projection::Perspective
+            } else {
+                Projection::Orthographic
+            };
+
+            let selection_expr = selection.get();
+            let compiled_selection = if selection_expr.trim().is_empty() {
+                None
+            } else {
+                match selection::parse(&selection_expr) {
+                    Ok(expr) => Some(expr),
+                    Err(err) => {
+                        eprintln!("ERROR: bad selection '{selection_expr}': {err}");
+                        None
+                    }
+                }
+            };
+            let visible_atoms: Vec<_> = playback
+                .current()
+                .atoms
+                .iter()
+                .filter(|atom| {
+                    compiled_selection
+                        .as_ref()
+                        .map_or(true, |expr| expr.matches(atom))
+                })
+                .collect();
+
+            let render_result = if let Some(gpu_renderer) = &mut gpu_renderer {
+                pixels.render_with(|encoder, render_target, context| {
+                    gpu_renderer.render(
+                        encoder,
+                        render_target,
+                        context,
+                        &visible_atoms,
+                        &camera,
+                        zoom.get(),
+                        atom_size.get().max(1),
+                        depth_shading.get(),
+                        background.get().0,
+                        width,
+                        height,
+                    );
+                    Ok(())
+                })
+            } else {
+                let atom_size = atom_size.get().max(1);
+                let bg = background.get().0;
+
+                // Render the pixels.
+                let frame = pixels.frame_mut();
+                for pixel in frame.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&[bg[0], bg[1], bg[2], 0xff]);
+                }
+                const PIXEL_SIZE: usize = 4;
+                let screen_center = Vec2::new(width as f32 / 2.0, height as f32 / 2.0);
+                let x_range = 0..width;
+                let y_range = 0..height;
+
+                // Rotate every atom into camera space up front, so depth shading can be
+                // normalized over the rotated z-extent rather than the structure's static one.
+                let rotated: Vec<Vec3> = visible_atoms
+                    .iter()
+                    .map(|atom| camera.to_camera_space(atom.position))
+                    .collect();
+                let min_z = rotated.iter().fold(f32::INFINITY, |acc, v| acc.min(v.z));
+                let max_z = rotated
+                    .iter()
+                    .fold(f32::NEG_INFINITY, |acc, v| acc.max(v.z));
+                let z_range = (max_z - min_z).max(f32::EPSILON);
+
+                for camera_space in &rotated {
+                    // Project from camera space to screen space.
+                    let pos = camera.project(*camera_space);
+
+                    // Render that onto the screen.
+                    let screen_pos = pos * zoom.get() + screen_center;
+                    let (x, y) = (screen_pos.x as u32, screen_pos.y as u32);
+                    if x_range.contains(&x) && y_range.contains(&y) {
+                        // Closer atoms (larger z, towards the camera) render brighter.
+                        let depth = if depth_shading.get() {
+                            (camera_space.z - min_z) / z_range
+                        } else {
+                            1.0
+                        };
+                        let px = {
+                            let v = (depth * u8::MAX as f32 + 10.0) as u8;
+                            [v, v, v, 0xff]
+                        };
+                        for dx in 0..atom_size {
+                            for dy in 0..atom_size {
+                                let (x, y) = (x + dx, y + dy);
+                                let idx = (y * width + x) as usize * PIXEL_SIZE;
+                                if idx + PIXEL_SIZE >= frame.len() {
+                                    continue;
+                                }
+                                frame[idx..idx + PIXEL_SIZE].copy_from_slice(&px);
+                            }
                         }
-                        frame[idx..idx + PIXEL_SIZE].copy_from_slice(&px);
                     }
                 }
-            }
+
+                pixels.render()
+            };
 
             // Try to render.
-            if let Err(err) = pixels.render() {
+            if let Err(err) = render_result {
                 eprintln!("ERROR: {err}");
                 *control_flow = ControlFlow::Exit;
                 return;
             }
         }
 
+        if let Event::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(c),
+            ..
+        } = &event
+        {
+            if console.is_open() {
+                match c {
+                    // Swallowed: the backtick toggles the console and is never typed into it.
+                    '`' => {}
+                    '\r' | '\n' => console.submit(&cvars),
+                    '\u{8}' => console.backspace(),
+                    c if !c.is_control() => console.push_char(*c),
+                    _ => {}
+                }
+                eprintln!("CONSOLE> {}", console.input_line());
+                if !console.last_output().is_empty() {
+                    eprintln!("CONSOLE< {}", console.last_output());
+                }
+            }
+        }
+
         if input.update(&event) {
             // Close events.
             if input.close_requested() {
+                if let Err(err) = cvars.save_to_file(config_path) {
+                    eprintln!("WARN:  Failed to save '{CONFIG_PATH}': {err}");
+                }
                 eprintln!("INFO:  Close requested. Bye :)");
                 *control_flow = ControlFlow::Exit;
                 return;
@@ -135,23 +457,86 @@ fn main() -> Result<(), pixels::Error> {
                 }
             }
 
-            // Deal with key input.
-            if input.key_held(VirtualKeyCode::Up) {
-                // Rotate up.
-            }
-            if input.key_held(VirtualKeyCode::Down) {
-                // Rotate down.
-            }
-            if input.key_held(VirtualKeyCode::Left) {
-                // Rotate left.
-            }
-            if input.key_held(VirtualKeyCode::Right) {
-                // Rotate right.
+            // The backtick key toggles the console, whether it is open or closed.
+            if input.key_pressed(VirtualKeyCode::Grave) {
+                console.toggle();
             }
 
-            zoom = 0.0f32.max(zoom + input.scroll_diff() * 3.0);
+            if !console.is_open() {
+                // Deal with key input.
+                if input.key_held(VirtualKeyCode::Up) {
+                    camera.rotate(0.0, -KEY_ROTATE_SPEED);
+                }
+                if input.key_held(VirtualKeyCode::Down) {
+                    camera.rotate(0.0, KEY_ROTATE_SPEED);
+                }
+                if input.key_held(VirtualKeyCode::Left) {
+                    camera.rotate(-KEY_ROTATE_SPEED, 0.0);
+                }
+                if input.key_held(VirtualKeyCode::Right) {
+                    camera.rotate(KEY_ROTATE_SPEED, 0.0);
+                }
+                if input.key_pressed(VirtualKeyCode::P) {
+                    perspective.set(!perspective.get());
+                }
+                if input.key_pressed(VirtualKeyCode::C) {
+                    let selection_expr = selection.get();
+                    if selection_expr.trim().is_empty() {
+                        playback.center_structure();
+                        eprintln!("INFO:  Centered on all atoms");
+                    } else {
+                        match selection::parse(&selection_expr) {
+                            Ok(expr) => {
+                                playback.center_on_selection(&expr);
+                                eprintln!("INFO:  Centered on selection '{selection_expr}'");
+                            }
+                            Err(err) => {
+                                eprintln!("ERROR: bad selection '{selection_expr}': {err}")
+                            }
+                        }
+                    }
+                }
+                if input.key_pressed(VirtualKeyCode::S) {
+                    let result = if let Some(gpu_renderer) = &gpu_renderer {
+                        let (width, height) = gpu_renderer.capture_size();
+                        let bytes = gpu_renderer.take_screenshot(pixels.device());
+                        screenshot::save_rgba(width, height, &bytes)
+                    } else {
+                        screenshot::save_screenshot(&pixels)
+                    };
+                    match result {
+                        Ok(path) => eprintln!("INFO:  Screenshot saved to '{}'", path.display()),
+                        Err(err) => eprintln!("ERROR: Failed to save screenshot: {err}"),
+                    }
+                }
+
+                // Trackball: drag with the left mouse button held.
+                if input.mouse_held(0) {
+                    let (dx, dy) = input.mouse_diff();
+                    camera.rotate(dx * MOUSE_ROTATE_SPEED, dy * MOUSE_ROTATE_SPEED);
+                }
+
+                // Trajectory playback.
+                if input.key_pressed(VirtualKeyCode::Space) {
+                    playback.toggle_playing();
+                }
+                if input.key_pressed(VirtualKeyCode::RBracket) {
+                    playback.step(true);
+                }
+                if input.key_pressed(VirtualKeyCode::LBracket) {
+                    playback.step(false);
+                }
+
+                zoom.set(0.0f32.max(zoom.get() + input.scroll_diff() * 3.0));
+            }
 
             window.request_redraw();
         }
+
+        if let Event::MainEventsCleared = event {
+            if playback.tick() {
+                window.request_redraw();
+            }
+        }
     });
 }
@@ -0,0 +1,33 @@
+//! PNG screenshot export of the current render.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pixels::{wgpu::Extent3d, Pixels};
+
+/// Reads back the current frame from `pixels`'s CPU framebuffer and writes it to a timestamped
+/// `.png` in the working directory. Returns the path written to.
+///
+/// Only valid for the CPU render path; when the GPU renderer is active, `pixels`'s CPU
+/// framebuffer is never written to, so use [`save_rgba`] with [`GpuRenderer::take_screenshot`]
+/// instead.
+///
+/// [`GpuRenderer::take_screenshot`]: crate::gpu_renderer::GpuRenderer::take_screenshot
+pub fn save_screenshot(pixels: &Pixels) -> Result<PathBuf, image::ImageError> {
+    let Extent3d { width, height, .. } = pixels.texture().size();
+    save_rgba(width, height, pixels.frame())
+}
+
+/// Writes tightly-packed RGBA8 `pixels` of size `width`x`height` to a timestamped `.png` in the
+/// working directory. Returns the path written to.
+pub fn save_rgba(width: u32, height: u32, pixels: &[u8]) -> Result<PathBuf, image::ImageError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the Unix epoch")
+        .as_secs();
+    let path = PathBuf::from(format!("laurel-{timestamp}.png"));
+
+    image::save_buffer(&path, pixels, width, height, image::ColorType::Rgba8)?;
+
+    Ok(path)
+}
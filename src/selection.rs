@@ -0,0 +1,172 @@
+//! A small selection mini-language compiled to a predicate over [`Atom`].
+//!
+//! Supports `resname X`, `atomname CA`, `resnum 1-20`, `z < 3.5`, and boolean `and`/`or`/`not`
+//! with parentheses, e.g. `resname POPC and not (atomname NA or atomname CL)`.
+
+use crate::structure::Atom;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Resname(String),
+    Atomname(String),
+    ResnumRange(u32, u32),
+    Z(Comparison, f32),
+    And(Box<Selection>, Box<Selection>),
+    Or(Box<Selection>, Box<Selection>),
+    Not(Box<Selection>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl Comparison {
+    fn apply(self, a: f32, b: f32) -> bool {
+        match self {
+            Comparison::Lt => a < b,
+            Comparison::Le => a <= b,
+            Comparison::Gt => a > b,
+            Comparison::Ge => a >= b,
+            Comparison::Eq => a == b,
+        }
+    }
+}
+
+impl Selection {
+    /// Returns whether `atom` matches this selection.
+    pub fn matches(&self, atom: &Atom) -> bool {
+        match self {
+            Selection::Resname(name) => atom.resname.as_str().trim() == name,
+            Selection::Atomname(name) => atom.atomname.as_str().trim() == name,
+            Selection::ResnumRange(lo, hi) => (*lo..=*hi).contains(&atom.resnum),
+            Selection::Z(cmp, value) => cmp.apply(atom.position.z, *value),
+            Selection::And(a, b) => a.matches(atom) && b.matches(atom),
+            Selection::Or(a, b) => a.matches(atom) || b.matches(atom),
+            Selection::Not(a) => !a.matches(atom),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionError(String);
+
+impl std::fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+fn err(msg: impl Into<String>) -> SelectionError {
+    SelectionError(msg.into())
+}
+
+/// Parses a selection expression such as `resname POPC and z < 3.5`.
+pub fn parse(input: &str) -> Result<Selection, SelectionError> {
+    let spaced = input.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    if tokens.is_empty() {
+        return Err(err("empty selection"));
+    }
+
+    let mut pos = 0;
+    let expr = parse_or(&tokens, &mut pos)?;
+    if pos != tokens.len() {
+        return Err(err(format!(
+            "unexpected trailing input near '{}'",
+            tokens[pos]
+        )));
+    }
+    Ok(expr)
+}
+
+fn parse_or(tokens: &[&str], pos: &mut usize) -> Result<Selection, SelectionError> {
+    let mut lhs = parse_and(tokens, pos)?;
+    while tokens.get(*pos) == Some(&"or") {
+        *pos += 1;
+        let rhs = parse_and(tokens, pos)?;
+        lhs = Selection::Or(Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_and(tokens: &[&str], pos: &mut usize) -> Result<Selection, SelectionError> {
+    let mut lhs = parse_unary(tokens, pos)?;
+    while tokens.get(*pos) == Some(&"and") {
+        *pos += 1;
+        let rhs = parse_unary(tokens, pos)?;
+        lhs = Selection::And(Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_unary(tokens: &[&str], pos: &mut usize) -> Result<Selection, SelectionError> {
+    match tokens.get(*pos) {
+        Some(&"not") => {
+            *pos += 1;
+            Ok(Selection::Not(Box::new(parse_unary(tokens, pos)?)))
+        }
+        Some(&"(") => {
+            *pos += 1;
+            let inner = parse_or(tokens, pos)?;
+            match tokens.get(*pos) {
+                Some(&")") => {
+                    *pos += 1;
+                    Ok(inner)
+                }
+                _ => Err(err("expected closing ')'")),
+            }
+        }
+        _ => parse_atom(tokens, pos),
+    }
+}
+
+fn next_token<'a>(tokens: &[&'a str], pos: &mut usize) -> Result<&'a str, SelectionError> {
+    let token = *tokens
+        .get(*pos)
+        .ok_or_else(|| err("unexpected end of selection"))?;
+    *pos += 1;
+    Ok(token)
+}
+
+fn parse_atom(tokens: &[&str], pos: &mut usize) -> Result<Selection, SelectionError> {
+    let keyword = next_token(tokens, pos)?;
+    match keyword {
+        "resname" => Ok(Selection::Resname(next_token(tokens, pos)?.to_string())),
+        "atomname" => Ok(Selection::Atomname(next_token(tokens, pos)?.to_string())),
+        "resnum" => {
+            let range = next_token(tokens, pos)?;
+            let (lo, hi) = range
+                .split_once('-')
+                .ok_or_else(|| err(format!("expected 'resnum A-B', got '{range}'")))?;
+            let lo = lo
+                .parse()
+                .map_err(|_| err(format!("bad resnum range start '{lo}'")))?;
+            let hi = hi
+                .parse()
+                .map_err(|_| err(format!("bad resnum range end '{hi}'")))?;
+            Ok(Selection::ResnumRange(lo, hi))
+        }
+        "z" => {
+            let cmp = match next_token(tokens, pos)? {
+                "<" => Comparison::Lt,
+                "<=" => Comparison::Le,
+                ">" => Comparison::Gt,
+                ">=" => Comparison::Ge,
+                "==" => Comparison::Eq,
+                other => return Err(err(format!("unknown comparison operator '{other}'"))),
+            };
+            let value = next_token(tokens, pos)?
+                .parse()
+                .map_err(|_| err("expected a number after the comparison operator"))?;
+            Ok(Selection::Z(cmp, value))
+        }
+        other => Err(err(format!("unknown selection keyword '{other}'"))),
+    }
+}
@@ -1,6 +1,12 @@
 use arraystring::{typenum::U5, ArrayString};
 use glam::Vec3;
 
+use crate::selection::Selection;
+use crate::xdr::{XdrError, XdrReader};
+
+/// Magic number every `.trr` frame header starts with.
+const TRR_MAGIC: i32 = 1993;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Atom {
     // 5 positions, integer.
@@ -80,60 +86,177 @@ impl Structure {
 
         max_z
     }
+
+    /// Returns the indices of atoms that match `selection`.
+    pub fn select(&self, selection: &Selection) -> Vec<usize> {
+        self.atoms
+            .iter()
+            .enumerate()
+            .filter(|(_, atom)| selection.matches(atom))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Like [`Structure::center`], but only over the atoms at `indices`.
+    ///
+    /// If `indices` is empty, a zero vector is returned.
+    pub fn center_of(&self, indices: &[usize]) -> Vec3 {
+        if indices.is_empty() {
+            return Vec3::ZERO;
+        }
+        let sum = indices
+            .iter()
+            .fold(Vec3::ZERO, |acc, &i| acc + self.atoms[i].position);
+        sum / indices.len() as f32
+    }
+
+    /// Like [`Structure::center_structure`], but centers on the centroid of `indices` rather
+    /// than the whole structure. Every atom, not just the selected ones, is shifted by the same
+    /// offset.
+    pub fn center_structure_on(&mut self, indices: &[usize]) {
+        let center = self.center_of(indices);
+        self.atoms
+            .iter_mut()
+            .for_each(|atom| atom.position -= center);
+    }
+}
+
+/// An error encountered while parsing a Gromacs `.gro` file with [`Structure::from_gro`].
+///
+/// Line numbers are 1-based, matching the numbering a text editor would show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroError {
+    /// The file ended before the title, atom count, an atom line, or the box vector line could
+    /// be read.
+    TooShort,
+    /// The atom count on line 2 was not a valid integer.
+    BadAtomCount { line: usize },
+    /// An atom line was shorter than the fixed-width column it was being read from.
+    ShortAtomLine { line: usize, len: usize },
+    /// A fixed-width field on an atom line did not parse as its expected type.
+    BadField {
+        line: usize,
+        column: std::ops::Range<usize>,
+        field: &'static str,
+    },
+    /// The final box vector line did not contain at least the three diagonal vectors.
+    BadBoxVectors { line: usize },
+}
+
+impl std::fmt::Display for GroError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroError::TooShort => write!(f, "gro file ended before a required line was reached"),
+            GroError::BadAtomCount { line } => {
+                write!(f, "line {line}: invalid atom count")
+            }
+            GroError::ShortAtomLine { line, len } => write!(
+                f,
+                "line {line}: atom line is only {len} characters long"
+            ),
+            GroError::BadField {
+                line,
+                column,
+                field,
+            } => write!(
+                f,
+                "line {line}, columns {}..{}: bad {field}",
+                column.start, column.end
+            ),
+            GroError::BadBoxVectors { line } => write!(f, "line {line}: bad box vectors"),
+        }
+    }
 }
 
+impl std::error::Error for GroError {}
+
 impl Structure {
     // TODO: Actually check whether assuming ascii where possible is valid. Cannot imagine it is
     // not valid though.
-    // TODO: In that same vain, perhaps actually implement an error system for this :/
     /// Read a string in Gromacs `.gro` format to a [`Structure`].
-    pub fn from_gro(gro: String) -> Result<Self, String> {
-        let mut lines = gro.lines();
-        let title = String::from(lines.next().expect("too short file").trim());
-        let n_atoms = lines
-            .next()
-            .expect("too short file")
+    pub fn from_gro(gro: String) -> Result<Self, GroError> {
+        let mut lines = gro.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let (_, title_line) = lines.next().ok_or(GroError::TooShort)?;
+        let title = String::from(title_line.trim());
+
+        let (n_atoms_line, n_atoms_str) = lines.next().ok_or(GroError::TooShort)?;
+        let n_atoms: usize = n_atoms_str
             .trim()
             .parse()
-            .expect("invalid n_atoms integer at line 2 of the gro file");
+            .map_err(|_| GroError::BadAtomCount { line: n_atoms_line })?;
+
+        fn field<'a>(
+            line_no: usize,
+            line: &'a str,
+            column: std::ops::Range<usize>,
+        ) -> Result<&'a str, GroError> {
+            line.get(column).ok_or(GroError::ShortAtomLine {
+                line: line_no,
+                len: line.len(),
+            })
+        }
+
+        fn parse_field<T: std::str::FromStr>(
+            line_no: usize,
+            line: &str,
+            column: std::ops::Range<usize>,
+            name: &'static str,
+        ) -> Result<T, GroError> {
+            field(line_no, line, column.clone())?
+                .trim()
+                .parse()
+                .map_err(|_| GroError::BadField {
+                    line: line_no,
+                    column,
+                    field: name,
+                })
+        }
 
         let mut atoms = Vec::with_capacity(n_atoms);
         for _ in 0..n_atoms {
-            let line = lines
-                .next()
-                .expect("end of file before all atoms have been specified");
+            let (line_no, line) = lines.next().ok_or(GroError::TooShort)?;
             let atom = Atom {
-                resnum: line[0..5].trim().parse().expect("bad resnum integer"),
-                resname: line[5..10].trim().try_into().unwrap(), // We know that the length <= 5.
-                atomname: line[10..15].trim().try_into().unwrap(),
-                atomnum: line[15..20].trim().parse().expect("bad atomnum integer"),
-                position: {
-                    // TODO: Is this nicer?? vvv
-                    // [0, 1, 2]
-                    //     .map(|i| i * 8)
-                    //     .map(|i| line[i..i + 8].parse::<f32>().expect("bad position float"))
-                    //     .into()
-                    [&line[20..28], &line[28..36], &line[36..44]]
-                        .map(|v| v.trim().parse::<f32>().expect("bad position float"))
-                        .into()
-                },
+                resnum: parse_field(line_no, line, 0..5, "resnum")?,
+                resname: field(line_no, line, 5..10)?
+                    .trim()
+                    .try_into()
+                    .map_err(|_| GroError::BadField {
+                        line: line_no,
+                        column: 5..10,
+                        field: "resname",
+                    })?,
+                atomname: field(line_no, line, 10..15)?
+                    .trim()
+                    .try_into()
+                    .map_err(|_| GroError::BadField {
+                        line: line_no,
+                        column: 10..15,
+                        field: "atomname",
+                    })?,
+                atomnum: parse_field(line_no, line, 15..20, "atomnum")?,
+                position: Vec3::new(
+                    parse_field(line_no, line, 20..28, "position.x")?,
+                    parse_field(line_no, line, 28..36, "position.y")?,
+                    parse_field(line_no, line, 36..44, "position.z")?,
+                ),
             };
             atoms.push(atom);
         }
 
+        let (box_line_no, box_line) = lines.next().ok_or(GroError::TooShort)?;
+        let bad_box_vectors = || GroError::BadBoxVectors { line: box_line_no };
         let mut box_vecs: [f32; 9] = Default::default();
-        let mut box_line = lines
-            .next()
-            .expect("too short file")
+        let mut box_fields = box_line
             .split_ascii_whitespace()
             .map(|v| v.parse::<f32>().ok());
-        let [v1x, v2y, v3z] = box_line
-            .next_chunk()
-            .expect("bad box vectors")
-            .map(|v| v.expect("bad first box vector triplet"));
-        box_vecs[0..3].copy_from_slice(&[v1x, v2y, v3z]);
+        let [v1x, v2y, v3z] = box_fields.next_chunk().map_err(|_| bad_box_vectors())?;
+        box_vecs[0..3].copy_from_slice(&[
+            v1x.ok_or_else(bad_box_vectors)?,
+            v2y.ok_or_else(bad_box_vectors)?,
+            v3z.ok_or_else(bad_box_vectors)?,
+        ]);
         if let [Some(v1y), Some(v1z), Some(v2x), Some(v2z), Some(v3x), Some(v3y)] =
-            box_line.collect::<Vec<_>>()[..]
+            box_fields.collect::<Vec<_>>()[..]
         {
             box_vecs[3..].copy_from_slice(&[v1y, v1z, v2x, v2z, v3x, v3y]);
         }
@@ -145,3 +268,179 @@ impl Structure {
         })
     }
 }
+
+/// The floating-point precision a `.trr` frame's reals were written with.
+///
+/// Determined per-frame from the header's `box_size`: GROMACS writes `box_size` as
+/// `9 * size_of(real)`, so a `box_size` of 36 means `f32` reals and 72 means `f64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Precision {
+    F32,
+    F64,
+}
+
+impl Precision {
+    fn size(self) -> usize {
+        match self {
+            Precision::F32 => 4,
+            Precision::F64 => 8,
+        }
+    }
+
+    fn read(self, reader: &mut XdrReader) -> Result<f64, XdrError> {
+        match self {
+            Precision::F32 => reader.read_f32().map(|v| v as f64),
+            Precision::F64 => reader.read_f64(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrrError {
+    /// A frame's magic number was not `1993`.
+    BadMagic { found: i32 },
+    Xdr(XdrError),
+}
+
+impl std::fmt::Display for TrrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrrError::BadMagic { found } => {
+                write!(f, "bad .trr magic number: expected 1993, found {found}")
+            }
+            TrrError::Xdr(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for TrrError {}
+
+impl From<XdrError> for TrrError {
+    fn from(err: XdrError) -> Self {
+        TrrError::Xdr(err)
+    }
+}
+
+/// A sequence of [`Structure`] frames loaded from a GROMACS trajectory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    pub(crate) frames: Vec<Structure>,
+}
+
+impl Trajectory {
+    /// Returns the number of frames in this trajectory.
+    pub fn n_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the frame at `index`, if any.
+    pub fn frame(&self, index: usize) -> Option<&Structure> {
+        self.frames.get(index)
+    }
+}
+
+impl Structure {
+    /// Read a GROMACS binary `.trr` trajectory to a [`Trajectory`].
+    ///
+    /// A `.trr` frame carries no residue or atom names, so placeholder `resname`/`atomname`
+    /// values are synthesized. Use [`Structure::from_trr_with_topology`] to carry over real
+    /// names from a companion `.gro` instead.
+    pub fn from_trr(bytes: &[u8]) -> Result<Trajectory, TrrError> {
+        Self::from_trr_with_topology(bytes, None)
+    }
+
+    /// Like [`Structure::from_trr`], but fills in `resname`/`atomname`/`resnum` for each atom
+    /// from `topology` (by atom index) instead of using placeholders.
+    pub fn from_trr_with_topology(
+        bytes: &[u8],
+        topology: Option<&Structure>,
+    ) -> Result<Trajectory, TrrError> {
+        let placeholder_resname: ArrayString<U5> = "UNK".try_into().unwrap();
+        let placeholder_atomname: ArrayString<U5> = "X".try_into().unwrap();
+
+        let mut reader = XdrReader::new(bytes);
+        let mut frames = Vec::new();
+
+        while !reader.is_empty() {
+            let magic = reader.read_i32()?;
+            if magic != TRR_MAGIC {
+                return Err(TrrError::BadMagic { found: magic });
+            }
+            let _version = reader.read_string()?;
+
+            let _ir_size = reader.read_i32()?;
+            let _e_size = reader.read_i32()?;
+            let box_size = reader.read_i32()?;
+            let vir_size = reader.read_i32()?;
+            let pres_size = reader.read_i32()?;
+            let _top_size = reader.read_i32()?;
+            let _sym_size = reader.read_i32()?;
+            let x_size = reader.read_i32()?;
+            let v_size = reader.read_i32()?;
+            let f_size = reader.read_i32()?;
+            let natoms = reader.read_i32()?.max(0) as usize;
+            let _step = reader.read_i32()?;
+            let _nre = reader.read_i32()?;
+
+            let precision = if box_size / 9 == 4 {
+                Precision::F32
+            } else {
+                Precision::F64
+            };
+
+            let _t = precision.read(&mut reader)?;
+            let _lambda = precision.read(&mut reader)?;
+
+            let mut box_vecs = [0.0f32; 9];
+            if box_size > 0 {
+                for v in box_vecs.iter_mut() {
+                    *v = precision.read(&mut reader)? as f32;
+                }
+            }
+
+            if vir_size > 0 {
+                for _ in 0..9 {
+                    precision.read(&mut reader)?;
+                }
+            }
+            if pres_size > 0 {
+                for _ in 0..9 {
+                    precision.read(&mut reader)?;
+                }
+            }
+
+            let mut atoms = Vec::with_capacity(natoms);
+            if x_size > 0 {
+                for i in 0..natoms {
+                    let x = precision.read(&mut reader)? as f32;
+                    let y = precision.read(&mut reader)? as f32;
+                    let z = precision.read(&mut reader)? as f32;
+
+                    let template = topology.and_then(|t| t.atoms.get(i));
+                    atoms.push(Atom {
+                        resnum: template.map_or(0, |a| a.resnum),
+                        resname: template.map_or(placeholder_resname, |a| a.resname),
+                        atomname: template.map_or(placeholder_atomname, |a| a.atomname),
+                        atomnum: i as u32 + 1,
+                        position: Vec3::new(x, y, z),
+                    });
+                }
+            }
+
+            if v_size > 0 {
+                reader.skip(natoms * 3 * precision.size())?;
+            }
+            if f_size > 0 {
+                reader.skip(natoms * 3 * precision.size())?;
+            }
+
+            frames.push(Structure {
+                title: String::new(),
+                atoms,
+                box_vecs,
+            });
+        }
+
+        Ok(Trajectory { frames })
+    }
+}
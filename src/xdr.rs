@@ -0,0 +1,91 @@
+//! A small bounds-checked reader for XDR-encoded binary data.
+//!
+//! GROMACS `.trr` trajectory files store their payload as big-endian XDR, with strings padded up
+//! to a multiple of 4 bytes. This reader walks a byte slice with a cursor and returns an error
+//! instead of panicking when a read would run past the end of the buffer.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XdrError {
+    /// A read of `needed` bytes at offset `at` would run past the end of a buffer of `len`
+    /// bytes.
+    UnexpectedEof { at: usize, needed: usize, len: usize },
+}
+
+impl std::fmt::Display for XdrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XdrError::UnexpectedEof { at, needed, len } => write!(
+                f,
+                "unexpected end of data: tried to read {needed} bytes at offset {at}, but buffer is only {len} bytes long"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for XdrError {}
+
+/// A cursor over a byte slice that reads big-endian XDR primitives.
+pub struct XdrReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> XdrReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Returns the current byte offset of the cursor.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    /// Returns whether the cursor has reached the end of the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], XdrError> {
+        if self.pos + n > self.bytes.len() {
+            return Err(XdrError::UnexpectedEof {
+                at: self.pos,
+                needed: n,
+                len: self.bytes.len(),
+            });
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, XdrError> {
+        self.take(4).map(|b| i32::from_be_bytes(b.try_into().unwrap()))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, XdrError> {
+        self.take(4).map(|b| f32::from_be_bytes(b.try_into().unwrap()))
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, XdrError> {
+        self.take(8).map(|b| f64::from_be_bytes(b.try_into().unwrap()))
+    }
+
+    /// Reads an XDR string: an `i32` length `n`, followed by `n` bytes, padded up to a multiple
+    /// of 4 bytes.
+    pub fn read_string(&mut self) -> Result<String, XdrError> {
+        let n = self.read_i32()?.max(0) as usize;
+        let padded = (n + 3) / 4 * 4;
+        let bytes = self.take(padded)?;
+        Ok(String::from_utf8_lossy(&bytes[..n]).into_owned())
+    }
+
+    /// Skips `n` bytes without interpreting them.
+    pub fn skip(&mut self, n: usize) -> Result<(), XdrError> {
+        self.take(n).map(|_| ())
+    }
+}